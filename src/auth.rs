@@ -0,0 +1,181 @@
+//! Authentication strategies for the [`Typeform`](crate::Typeform) client.
+//!
+//! Besides a long-lived personal access token, the client can act on behalf of
+//! a user through Typeform's OAuth2 flow, transparently refreshing a
+//! short-lived access token once it is about to expire.
+//!
+//! Refreshing is split from the token exchange so the [`credentials`] mutex is
+//! only held while reading or writing the cached token, never across the
+//! network round-trip. The exchange itself comes in a blocking and an async
+//! flavour so the `async` client never blocks the executor thread.
+//!
+//! [`credentials`]: crate::Typeform
+
+use std::time::{Duration, SystemTime};
+
+use isahc::{prelude::*, Request};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, DEFAULT_TYPEFORM_URL};
+
+/// Path of Typeform's OAuth2 token endpoint.
+const TOKEN_PATH: &str = "/oauth/token";
+/// Refresh an access token this long before it actually expires.
+const EXPIRY_SKEW: Duration = Duration::from_secs(60);
+
+/// How a [`Typeform`](crate::Typeform) client authenticates its requests.
+#[derive(Clone, Debug)]
+pub enum Credentials {
+    /// A long-lived personal access token, used verbatim as the bearer token.
+    PersonalToken(String),
+    /// A short-lived OAuth2 access token plus the material required to refresh
+    /// it once it expires.
+    OAuth2 {
+        /// OAuth2 application client id.
+        client_id: String,
+        /// OAuth2 application client secret.
+        client_secret: String,
+        /// Long-lived refresh token obtained during the authorization flow.
+        refresh_token: String,
+        /// Current access token.
+        access_token: String,
+        /// Instant at which `access_token` stops being valid.
+        expires_at: SystemTime,
+    },
+}
+
+impl Credentials {
+    /// Inspect the cached token without performing any I/O.
+    ///
+    /// Returns either a ready-to-use bearer token or the parameters needed to
+    /// refresh an expired OAuth2 token. The caller is expected to run the
+    /// (blocking or async) exchange *outside* any held lock and then feed the
+    /// result back through [`store`](Self::store).
+    pub(crate) fn bearer(&self) -> Bearer {
+        match self {
+            Credentials::PersonalToken(token) => Bearer::Ready(token.clone()),
+            Credentials::OAuth2 {
+                client_id,
+                client_secret,
+                refresh_token,
+                access_token,
+                expires_at,
+            } => {
+                if is_expired(*expires_at) {
+                    Bearer::Refresh(RefreshParams {
+                        client_id: client_id.clone(),
+                        client_secret: client_secret.clone(),
+                        refresh_token: refresh_token.clone(),
+                    })
+                } else {
+                    Bearer::Ready(access_token.clone())
+                }
+            }
+        }
+    }
+
+    /// Cache a freshly exchanged token and return the new bearer value.
+    pub(crate) fn store(&mut self, token: TokenResponse) -> String {
+        match self {
+            Credentials::OAuth2 {
+                access_token,
+                expires_at,
+                refresh_token,
+                ..
+            } => {
+                *access_token = token.access_token;
+                *expires_at = SystemTime::now() + Duration::from_secs(token.expires_in);
+                if let Some(new_refresh_token) = token.refresh_token {
+                    *refresh_token = new_refresh_token;
+                }
+                access_token.clone()
+            }
+            Credentials::PersonalToken(token) => token.clone(),
+        }
+    }
+}
+
+/// Outcome of inspecting cached [`Credentials`] for a usable bearer token.
+#[derive(Clone, Debug)]
+pub(crate) enum Bearer {
+    /// The cached token is still valid; use it directly.
+    Ready(String),
+    /// The token expired and must be refreshed with these parameters.
+    Refresh(RefreshParams),
+}
+
+/// Everything needed to exchange a refresh token for a fresh access token.
+#[derive(Clone, Debug)]
+pub(crate) struct RefreshParams {
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+}
+
+impl RefreshParams {
+    /// `grant_type=refresh_token` form body for the token endpoint.
+    fn body(&self) -> Result<String, Error> {
+        Ok(serde_urlencoded::to_string(RefreshRequest {
+            grant_type: "refresh_token",
+            refresh_token: &self.refresh_token,
+            client_id: &self.client_id,
+            client_secret: &self.client_secret,
+        })?)
+    }
+
+    /// Blocking token exchange, used by the blocking client.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn exchange(&self) -> Result<TokenResponse, Error> {
+        let mut response = Request::post(format!("{}{}", DEFAULT_TYPEFORM_URL, TOKEN_PATH))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(self.body()?)?
+            .send()?;
+        if !response.status().is_success() {
+            return Err(Error::Api {
+                status: response.status().as_u16(),
+                body: response.text().unwrap_or_default(),
+            });
+        }
+        Ok(response.json()?)
+    }
+
+    /// Non-blocking token exchange, used by the async client so that a refresh
+    /// never stalls the executor thread.
+    #[cfg(feature = "async")]
+    pub(crate) async fn exchange_async(&self) -> Result<TokenResponse, Error> {
+        let mut response = Request::post(format!("{}{}", DEFAULT_TYPEFORM_URL, TOKEN_PATH))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(self.body()?)?
+            .send_async()
+            .await?;
+        if !response.status().is_success() {
+            return Err(Error::Api {
+                status: response.status().as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// `true` when `expires_at` is in the past or within the [`EXPIRY_SKEW`] window.
+fn is_expired(expires_at: SystemTime) -> bool {
+    SystemTime::now() + EXPIRY_SKEW >= expires_at
+}
+
+/// Body sent to the token endpoint to exchange a refresh token.
+#[derive(Serialize)]
+struct RefreshRequest<'a> {
+    grant_type: &'a str,
+    refresh_token: &'a str,
+    client_id: &'a str,
+    client_secret: &'a str,
+}
+
+/// Token endpoint response.
+#[derive(Debug, Deserialize)]
+pub(crate) struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    refresh_token: Option<String>,
+}
@@ -19,61 +19,457 @@
     variant_size_differences
 )]
 
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
 use isahc::{prelude::*, Request};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub mod auth;
+pub mod webhook;
 
-const DEFAULT_TYPEFORM_URL: &str = "https://api.typeform.com";
+pub use auth::Credentials;
+
+pub(crate) const DEFAULT_TYPEFORM_URL: &str = "https://api.typeform.com";
 const GET_FORM_RESPONSES_PATH: &str = "/forms/{form_id}/responses";
 
+/// Errors that can occur while talking to Typeform's API.
+///
+/// Marked `#[non_exhaustive]` so that new failure modes can be added without
+/// breaking downstream `match` statements.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The request could not be constructed (e.g. an invalid URL or header).
+    #[error("Failed to build a request: {0}")]
+    RequestBuild(#[from] isahc::http::Error),
+    /// The request could not be sent or the response could not be read.
+    #[error("Failed to send get request: {0}")]
+    Transport(#[from] isahc::Error),
+    /// Typeform answered with a non-success status code. `body` holds the raw
+    /// payload, which for errors like a `429` rate-limit carries a
+    /// `code`/`description` object describing the problem.
+    #[error("Typeform API returned status {status}: {body}")]
+    Api {
+        /// The HTTP status code of the response.
+        status: u16,
+        /// The raw response body.
+        body: String,
+    },
+    /// The response body did not match the expected shape.
+    #[error("Failed to deserialize a response: {0}")]
+    Deserialize(#[from] serde_json::Error),
+    /// A [`ResponsesQuery`] could not be encoded into a query string.
+    #[error("Failed to encode a query: {0}")]
+    QueryEncode(#[from] serde_urlencoded::ser::Error),
+    /// A webhook payload failed signature verification.
+    #[error("Webhook signature verification failed")]
+    InvalidSignature,
+}
+
 /// Main entry point to work with.
 #[derive(Debug)]
 pub struct Typeform {
     url: String,
     form_id: String,
-    token: String,
+    credentials: Mutex<Credentials>,
 }
 
 impl Typeform {
-    /// Default [`Typeform`] constructor.
+    /// Default [`Typeform`] constructor, using a personal access token.
     pub fn new(form_id: &str, token: &str) -> Typeform {
+        Typeform::with_credentials(form_id, Credentials::PersonalToken(token.to_owned()))
+    }
+
+    /// Construct a client authenticating with the given [`Credentials`], e.g.
+    /// an auto-refreshing [`Credentials::OAuth2`] token.
+    pub fn with_credentials(form_id: &str, credentials: Credentials) -> Typeform {
         Typeform {
             url: DEFAULT_TYPEFORM_URL.to_string(),
             form_id: form_id.to_string(),
-            token: token.to_owned(),
+            credentials: Mutex::new(credentials),
         }
     }
 
+    /// Resolve the `Authorization` header value, refreshing an expired OAuth2
+    /// token if needed.
+    ///
+    /// The `credentials` mutex is only held to read the cached token and to
+    /// write back a refreshed one; the token exchange itself runs with the
+    /// lock released so concurrent callers are not serialized behind the
+    /// network round-trip.
+    #[cfg(feature = "blocking")]
+    fn authorization(&self) -> Result<String, Error> {
+        let token = match self.bearer() {
+            auth::Bearer::Ready(token) => token,
+            auth::Bearer::Refresh(params) => {
+                let refreshed = params.exchange()?;
+                self.store_token(refreshed)
+            }
+        };
+        Ok(format!("Bearer {}", token))
+    }
+
+    /// Non-blocking counterpart of [`authorization`](Self::authorization); the
+    /// refresh exchange awaits instead of blocking the executor thread.
+    #[cfg(feature = "async")]
+    async fn authorization_async(&self) -> Result<String, Error> {
+        let token = match self.bearer() {
+            auth::Bearer::Ready(token) => token,
+            auth::Bearer::Refresh(params) => {
+                let refreshed = params.exchange_async().await?;
+                self.store_token(refreshed)
+            }
+        };
+        Ok(format!("Bearer {}", token))
+    }
+
+    /// Read the current bearer state under the credentials lock.
+    fn bearer(&self) -> auth::Bearer {
+        self.credentials
+            .lock()
+            .expect("credentials mutex was poisoned")
+            .bearer()
+    }
+
+    /// Cache a refreshed token under the credentials lock.
+    fn store_token(&self, token: auth::TokenResponse) -> String {
+        self.credentials
+            .lock()
+            .expect("credentials mutex was poisoned")
+            .store(token)
+    }
+
     /// Retrieve all [`Responses`].
-    pub fn responses(&self) -> Result<Responses, String> {
-        Request::get(format!(
-            "{}{}",
-            self.url,
-            GET_FORM_RESPONSES_PATH.replace("{form_id}", &self.form_id),
-        ))
-        .header("Authorization", format!("Bearer {}", &self.token))
-        .body(())
-        .map_err(|error| format!("Failed to build a request: {}", error))?
-        .send()
-        .map_err(|error| format!("Failed to send get request: {}", error))?
-        .json()
-        .map_err(|error| format!("Failed to deserialize a response: {}", error))
-    }
-
-    /// Retrieve all [`Responses`] which goes after response with [`token`].
-    pub fn responses_after(&self, token: &str) -> Result<Responses, String> {
-        Request::get(format!(
-            "{}{}?after={}&page_size=1",
-            self.url,
-            GET_FORM_RESPONSES_PATH.replace("{form_id}", &self.form_id),
-            token,
-        ))
-        .header("Authorization", format!("Bearer {}", &self.token))
-        .body(())
-        .map_err(|error| format!("Failed to build a request: {}", error))?
-        .send()
-        .map_err(|error| format!("Failed to send get request: {}", error))?
-        .json()
-        .map_err(|error| format!("Failed to deserialize a response: {}", error))
+    #[cfg(feature = "blocking")]
+    pub fn responses(&self) -> Result<Responses, Error> {
+        self.get(GET_FORM_RESPONSES_PATH.replace("{form_id}", &self.form_id))
+    }
+
+    /// Retrieve all [`Responses`] which goes after response with [`token`](Response).
+    #[cfg(feature = "blocking")]
+    pub fn responses_after(&self, token: &str) -> Result<Responses, Error> {
+        self.get(self.query_path(&Typeform::query().after(token).page_size(1).build())?)
+    }
+
+    /// Asynchronous counterpart of [`responses`](Self::responses).
+    #[cfg(feature = "async")]
+    pub async fn responses_async(&self) -> Result<Responses, Error> {
+        self.get_async(GET_FORM_RESPONSES_PATH.replace("{form_id}", &self.form_id))
+            .await
+    }
+
+    /// Asynchronous counterpart of [`responses_after`](Self::responses_after).
+    #[cfg(feature = "async")]
+    pub async fn responses_after_async(&self, token: &str) -> Result<Responses, Error> {
+        self.get_async(self.query_path(&Typeform::query().after(token).page_size(1).build())?)
+            .await
+    }
+
+    /// Start building a filtered query against the responses endpoint.
+    ///
+    /// See [`ResponsesQueryBuilder`] for the supported parameters.
+    pub fn query() -> ResponsesQueryBuilder {
+        ResponsesQueryBuilder::default()
+    }
+
+    /// Iterate over every [`Response`], fetching pages on demand.
+    ///
+    /// The returned [`ResponsesPager`] hides Typeform's cursor-based paging:
+    /// it fetches `page_size` responses at a time and refills itself whenever
+    /// its buffer empties, terminating once a page returns fewer than
+    /// `page_size` items.
+    #[cfg(feature = "blocking")]
+    pub fn responses_iter(&self, page_size: u16) -> ResponsesPager<'_> {
+        ResponsesPager {
+            typeform: self,
+            page_size,
+            buffer: VecDeque::new(),
+            after: None,
+            done: false,
+        }
+    }
+
+    /// Stream every [`Response`], fetching pages on demand.
+    ///
+    /// The `futures::Stream` counterpart of [`responses_iter`](Self::responses_iter);
+    /// the same cursor semantics and `sort` caveats apply. Every page — and any
+    /// OAuth2 token refresh it triggers — is fetched without blocking the
+    /// executor thread, so the whole stream is safe to drive inside an async
+    /// runtime.
+    #[cfg(feature = "async")]
+    pub fn responses_stream(
+        &self,
+        page_size: u16,
+    ) -> impl futures::Stream<Item = Result<Response, Error>> + '_ {
+        futures::stream::unfold(
+            (VecDeque::<Response>::new(), None::<String>, false),
+            move |(mut buffer, mut after, mut done)| async move {
+                loop {
+                    if let Some(item) = buffer.pop_front() {
+                        return Some((Ok(item), (buffer, after, done)));
+                    }
+                    if done {
+                        return None;
+                    }
+                    let mut builder = Typeform::query().page_size(page_size);
+                    if let Some(after) = &after {
+                        builder = builder.after(after);
+                    }
+                    match self.responses_query_async(&builder.build()).await {
+                        Ok(page) => {
+                            let count = page.items.len();
+                            if let Some(last) = page.items.last() {
+                                after = Some(last.token.clone());
+                            }
+                            buffer.extend(page.items);
+                            if count < page_size as usize {
+                                done = true;
+                            }
+                        }
+                        Err(error) => {
+                            return Some((Err(error), (buffer, after, true)));
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    /// Retrieve [`Responses`] matching the given [`ResponsesQuery`].
+    ///
+    /// The query is URL-escaped via `serde_urlencoded`, so free-text `query`
+    /// values are safe and `None` fields are omitted from the request.
+    #[cfg(feature = "blocking")]
+    pub fn responses_query(&self, query: &ResponsesQuery) -> Result<Responses, Error> {
+        self.get(self.query_path(query)?)
+    }
+
+    /// Asynchronous counterpart of [`responses_query`](Self::responses_query).
+    #[cfg(feature = "async")]
+    pub async fn responses_query_async(&self, query: &ResponsesQuery) -> Result<Responses, Error> {
+        self.get_async(self.query_path(query)?).await
+    }
+
+    /// Build the path (with an optional query string) for a [`ResponsesQuery`].
+    fn query_path(&self, query: &ResponsesQuery) -> Result<String, Error> {
+        let path = GET_FORM_RESPONSES_PATH.replace("{form_id}", &self.form_id);
+        let encoded = serde_urlencoded::to_string(query)?;
+        Ok(if encoded.is_empty() {
+            path
+        } else {
+            format!("{}?{}", path, encoded)
+        })
+    }
+
+    /// Issue an authenticated GET against `path` and deserialize the body.
+    ///
+    /// The HTTP status is inspected before the body is parsed so that
+    /// Typeform's error payloads surface as a typed [`Error::Api`] instead of
+    /// a misleading deserialization failure.
+    #[cfg(feature = "blocking")]
+    fn get(&self, path: impl AsRef<str>) -> Result<Responses, Error> {
+        let mut response = Request::get(format!("{}{}", self.url, path.as_ref()))
+            .header("Authorization", self.authorization()?)
+            .body(())?
+            .send()?;
+        if !response.status().is_success() {
+            return Err(Error::Api {
+                status: response.status().as_u16(),
+                body: response.text().unwrap_or_default(),
+            });
+        }
+        Ok(response.json()?)
+    }
+
+    /// Asynchronous counterpart of [`get`](Self::get).
+    #[cfg(feature = "async")]
+    async fn get_async(&self, path: impl AsRef<str>) -> Result<Responses, Error> {
+        let mut response = Request::get(format!("{}{}", self.url, path.as_ref()))
+            .header("Authorization", self.authorization_async().await?)
+            .body(())?
+            .send_async()
+            .await?;
+        if !response.status().is_success() {
+            return Err(Error::Api {
+                status: response.status().as_u16(),
+                body: response.text().await.unwrap_or_default(),
+            });
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// Type-safe set of filters for the `/forms/{form_id}/responses` endpoint.
+///
+/// Every field is optional; only the ones that are set end up in the query
+/// string. Build one with [`Typeform::query`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ResponsesQuery {
+    /// Limit to responses submitted after the given ISO 8601 datetime.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since: Option<String>,
+    /// Limit to responses submitted before the given ISO 8601 datetime.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    until: Option<String>,
+    /// Return responses landed before the response with this token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<String>,
+    /// Return responses landed after the response with this token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+    /// Filter by whether the response was completed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completed: Option<bool>,
+    /// Order of the responses (e.g. `submitted_at,desc`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<String>,
+    /// Free-text search across answers; URL-escaped automatically.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    /// Comma-separated list of field ids to restrict the answers to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<String>,
+    /// Filter by whether a response contains at least one answer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    answered: Option<bool>,
+    /// Maximum number of responses per page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_size: Option<u16>,
+}
+
+/// Builder for a [`ResponsesQuery`]; obtained from [`Typeform::query`].
+#[derive(Clone, Debug, Default)]
+pub struct ResponsesQueryBuilder {
+    query: ResponsesQuery,
+}
+
+impl ResponsesQueryBuilder {
+    /// Only responses submitted after this ISO 8601 datetime.
+    pub fn since(mut self, since: &str) -> Self {
+        self.query.since = Some(since.to_owned());
+        self
+    }
+
+    /// Only responses submitted before this ISO 8601 datetime.
+    pub fn until(mut self, until: &str) -> Self {
+        self.query.until = Some(until.to_owned());
+        self
+    }
+
+    /// Only responses landed before the response with this token.
+    pub fn before(mut self, before: &str) -> Self {
+        self.query.before = Some(before.to_owned());
+        self
+    }
+
+    /// Only responses landed after the response with this token.
+    pub fn after(mut self, after: &str) -> Self {
+        self.query.after = Some(after.to_owned());
+        self
+    }
+
+    /// Filter by completion status.
+    pub fn completed(mut self, completed: bool) -> Self {
+        self.query.completed = Some(completed);
+        self
+    }
+
+    /// Set the sort order (e.g. `submitted_at,desc`).
+    pub fn sort(mut self, sort: &str) -> Self {
+        self.query.sort = Some(sort.to_owned());
+        self
+    }
+
+    /// Free-text search across the answers.
+    pub fn query(mut self, query: &str) -> Self {
+        self.query.query = Some(query.to_owned());
+        self
+    }
+
+    /// Restrict the answers to the given field ids.
+    pub fn fields(mut self, fields: &str) -> Self {
+        self.query.fields = Some(fields.to_owned());
+        self
+    }
+
+    /// Filter by whether the response contains at least one answer.
+    pub fn answered(mut self, answered: bool) -> Self {
+        self.query.answered = Some(answered);
+        self
+    }
+
+    /// Maximum number of responses per page.
+    pub fn page_size(mut self, page_size: u16) -> Self {
+        self.query.page_size = Some(page_size);
+        self
+    }
+
+    /// Finish building and return the [`ResponsesQuery`].
+    pub fn build(self) -> ResponsesQuery {
+        self.query
+    }
+}
+
+/// Iterator that transparently walks every [`Response`] across pages.
+///
+/// Typeform sorts responses newest-first by default, so the cursor semantics
+/// only yield a stable, complete set when the underlying query uses a
+/// deterministic `sort` ordering. Per-page HTTP failures are surfaced as an
+/// [`Err`] item, after which the iterator stops.
+#[cfg(feature = "blocking")]
+#[derive(Debug)]
+pub struct ResponsesPager<'a> {
+    typeform: &'a Typeform,
+    page_size: u16,
+    buffer: VecDeque<Response>,
+    after: Option<String>,
+    done: bool,
+}
+
+#[cfg(feature = "blocking")]
+impl ResponsesPager<'_> {
+    /// Fetch the next page and refill the internal buffer.
+    fn fetch(&mut self) -> Result<(), Error> {
+        let mut builder = Typeform::query().page_size(self.page_size);
+        if let Some(after) = &self.after {
+            builder = builder.after(after);
+        }
+        let page = self.typeform.responses_query(&builder.build())?;
+        self.absorb(page);
+        Ok(())
+    }
+
+    /// Buffer a fetched page, advancing the cursor and terminating once a short
+    /// (or empty) page is seen.
+    fn absorb(&mut self, page: Responses) {
+        let count = page.items.len();
+        if let Some(last) = page.items.last() {
+            self.after = Some(last.token.clone());
+        }
+        self.buffer.extend(page.items);
+        if count < self.page_size as usize {
+            self.done = true;
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Iterator for ResponsesPager<'_> {
+    type Item = Result<Response, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buffer.is_empty() {
+            if self.done {
+                return None;
+            }
+            if let Err(error) = self.fetch() {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
     }
 }
 
@@ -99,12 +495,14 @@ pub struct Response {
     landed_at: String,
     /// Time that the form response was submitted. In ISO 8601 format, UTC time, to the second, with T as a delimiter between the date and time.
     submitted_at: String,
-    /// Metadata about a client's HTTP request.
-    metadata: Metadata,
+    /// Metadata about a client's HTTP request. Present on Responses-API
+    /// payloads but absent from webhook `form_response` objects.
+    metadata: Option<Metadata>,
     /// Subset of a complete form definition to be included with a submission.
     definition: Option<Definition>,
     answers: Option<Answers>,
-    calculated: Calculated,
+    /// Scoring results; only present when the form defines calculations.
+    calculated: Option<Calculated>,
 }
 
 /// Metadata about a client's HTTP request.
@@ -244,4 +642,56 @@ mod tests {
         let _responses: Responses =
             serde_json::from_reader(reader).expect("Failed to build responses from reader.");
     }
+
+    #[test]
+    fn responses_query_omits_none_fields_and_escapes_free_text() {
+        let query = Typeform::query().query("needs escaping & stuff").build();
+        let encoded = serde_urlencoded::to_string(&query).expect("Failed to encode a query.");
+        assert_eq!(encoded, "query=needs+escaping+%26+stuff");
+    }
+
+    #[test]
+    fn empty_responses_query_serializes_to_an_empty_string() {
+        let encoded =
+            serde_urlencoded::to_string(Typeform::query().build()).expect("Failed to encode a query.");
+        assert!(encoded.is_empty());
+    }
+
+    /// Build a [`Responses`] page holding one minimal response per token.
+    #[cfg(feature = "blocking")]
+    fn page(tokens: &[&str]) -> Responses {
+        let items: Vec<String> = tokens
+            .iter()
+            .map(|token| {
+                format!(
+                    r#"{{"token":"{token}","landed_at":"2021-01-01T00:00:00Z","submitted_at":"2021-01-01T00:00:01Z","metadata":{{"user_agent":"ua","referer":"ref","network_id":"net"}},"calculated":{{"score":0}}}}"#
+                )
+            })
+            .collect();
+        serde_json::from_str(&format!(r#"{{"items":[{}]}}"#, items.join(",")))
+            .expect("Failed to build a page.")
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn responses_pager_stops_after_a_short_page() {
+        let typeform = Typeform::new("form", "token");
+        let mut pager = typeform.responses_iter(2);
+        pager.absorb(page(&["a", "b"]));
+        pager.absorb(page(&["c"]));
+        let tokens: Vec<String> = std::iter::from_fn(|| pager.next())
+            .map(|response| response.expect("page should not error").token)
+            .collect();
+        assert_eq!(tokens, ["a", "b", "c"]);
+        assert!(pager.next().is_none());
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn responses_pager_yields_nothing_for_an_empty_first_page() {
+        let typeform = Typeform::new("form", "token");
+        let mut pager = typeform.responses_iter(2);
+        pager.absorb(page(&[]));
+        assert!(pager.next().is_none());
+    }
 }
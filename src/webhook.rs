@@ -0,0 +1,116 @@
+//! Parsing and authentication of Typeform webhook deliveries.
+//!
+//! Typeform can push a `form_response` event to a configured webhook instead of
+//! forcing the integration to poll [`responses`](crate::Typeform::responses).
+//! Each delivery is signed, so [`parse_webhook`] verifies the signature before
+//! handing back a typed [`WebhookEvent`].
+
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::{Error, Response};
+
+/// A webhook delivery envelope wrapping a single form [`Response`].
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookEvent {
+    /// Unique id Typeform assigns to this delivery.
+    pub event_id: String,
+    /// Event type; currently always `form_response`.
+    pub event_type: String,
+    /// The submitted response carried by the event.
+    pub form_response: Response,
+}
+
+/// Verify a webhook delivery against the `Typeform-Signature` header.
+///
+/// The signature is the base64-encoded HMAC-SHA256 of the raw request body
+/// keyed with the configured `secret`, prefixed with `sha256=`. The comparison
+/// is constant-time to avoid leaking the expected value through timing.
+pub fn verify_signature(payload: &[u8], header: &str, secret: &str) -> bool {
+    let mut mac = match Hmac::<Sha256>::new_from_slice(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(payload);
+    let expected = format!("sha256={}", base64::encode(mac.finalize().into_bytes()));
+    constant_time_eq(expected.as_bytes(), header.as_bytes())
+}
+
+/// Verify then deserialize a webhook delivery into a [`WebhookEvent`].
+pub fn parse_webhook(
+    body: &[u8],
+    signature_header: &str,
+    secret: &str,
+) -> Result<WebhookEvent, Error> {
+    if !verify_signature(body, signature_header, secret) {
+        return Err(Error::InvalidSignature);
+    }
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// Compare two byte slices without short-circuiting on the first difference.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for (a, b) in left.iter().zip(right.iter()) {
+        diff |= a ^ b;
+    }
+    diff == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_signature_accepts_a_matching_header() {
+        let payload = b"{\"event_id\":\"abc\"}";
+        let secret = "topsecret";
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(payload);
+        let header = format!("sha256={}", base64::encode(mac.finalize().into_bytes()));
+        assert!(verify_signature(payload, &header, secret));
+    }
+
+    #[test]
+    fn verify_signature_rejects_a_tampered_payload() {
+        let secret = "topsecret";
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(b"{\"event_id\":\"abc\"}");
+        let header = format!("sha256={}", base64::encode(mac.finalize().into_bytes()));
+        assert!(!verify_signature(b"{\"event_id\":\"xyz\"}", &header, secret));
+    }
+
+    /// A representative `form_response` delivery: no `metadata`, no `calculated`.
+    const FORM_RESPONSE: &[u8] = br#"{"event_id":"LtWXD3crgy","event_type":"form_response","form_response":{"form_id":"lT4Z3j","token":"a3a12ec67a1365927098a606107fac15","landed_at":"2021-01-01T00:00:00Z","submitted_at":"2021-01-01T00:00:05Z","answers":[{"field":{"id":"hVONkQ","type":"short_text","ref":"name"},"type":"text","text":"Alice"}]}}"#;
+
+    fn sign(body: &[u8], secret: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", base64::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn parse_webhook_round_trips_a_signed_delivery() {
+        let secret = "topsecret";
+        let header = sign(FORM_RESPONSE, secret);
+        let event = parse_webhook(FORM_RESPONSE, &header, secret).expect("a signed webhook parses");
+        assert_eq!(event.event_id, "LtWXD3crgy");
+        assert_eq!(event.event_type, "form_response");
+        assert_eq!(
+            event.form_response.token,
+            "a3a12ec67a1365927098a606107fac15"
+        );
+    }
+
+    #[test]
+    fn parse_webhook_rejects_a_bad_signature() {
+        assert!(matches!(
+            parse_webhook(FORM_RESPONSE, "sha256=not-the-real-mac", "topsecret"),
+            Err(Error::InvalidSignature)
+        ));
+    }
+}